@@ -0,0 +1,299 @@
+use crate::config::AppKind;
+use crate::{ThemePreference, home_dir, run_command};
+use anyhow::{Context, Result};
+use log::debug;
+use regex::Regex;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A themeing backend that knows how to apply a single light/dark variant.
+pub trait Themeable {
+    fn apply(&self, theme: ThemePreference) -> Result<()>;
+
+    /// Describe what `apply(theme)` would do, without doing it.
+    fn describe(&self, theme: ThemePreference) -> String;
+}
+
+impl Themeable for AppKind {
+    fn apply(&self, theme: ThemePreference) -> Result<()> {
+        match self {
+            AppKind::Replace {
+                path,
+                light_token,
+                dark_token,
+                regex,
+            } => {
+                let (from, to) = match theme {
+                    ThemePreference::Dark => (light_token.as_str(), dark_token.as_str()),
+                    ThemePreference::Light => (dark_token.as_str(), light_token.as_str()),
+                };
+                let resolved = home_dir()?.join(path);
+                replace_in_file(&resolved, from, to, regex.as_deref())
+            }
+            AppKind::Gsettings {
+                schema,
+                key,
+                light_value,
+                dark_value,
+            } => {
+                let value = match theme {
+                    ThemePreference::Dark => dark_value,
+                    ThemePreference::Light => light_value,
+                };
+                set_gsettings(schema, key, value)
+            }
+            AppKind::Command {
+                light_cmd,
+                dark_cmd,
+            } => {
+                let command = match theme {
+                    ThemePreference::Dark => dark_cmd,
+                    ThemePreference::Light => light_cmd,
+                };
+                run_command(command)
+            }
+            AppKind::Symlink {
+                light_path,
+                dark_path,
+                target,
+            } => {
+                let source = match theme {
+                    ThemePreference::Dark => dark_path,
+                    ThemePreference::Light => light_path,
+                };
+                let home = home_dir()?;
+                symlink_swap(&home.join(source), &home.join(target))
+            }
+        }
+    }
+
+    fn describe(&self, theme: ThemePreference) -> String {
+        match self {
+            AppKind::Replace {
+                path,
+                light_token,
+                dark_token,
+                ..
+            } => {
+                let (from, to) = match theme {
+                    ThemePreference::Dark => (light_token, dark_token),
+                    ThemePreference::Light => (dark_token, light_token),
+                };
+                format!("replace `{from}` with `{to}` in {}", path.display())
+            }
+            AppKind::Gsettings {
+                schema,
+                key,
+                light_value,
+                dark_value,
+            } => {
+                let value = match theme {
+                    ThemePreference::Dark => dark_value,
+                    ThemePreference::Light => light_value,
+                };
+                format!("set {schema} {key} to `{value}`")
+            }
+            AppKind::Command {
+                light_cmd,
+                dark_cmd,
+            } => {
+                let command = match theme {
+                    ThemePreference::Dark => dark_cmd,
+                    ThemePreference::Light => light_cmd,
+                };
+                format!("run `{command}`")
+            }
+            AppKind::Symlink {
+                light_path,
+                dark_path,
+                target,
+            } => {
+                let source = match theme {
+                    ThemePreference::Dark => dark_path,
+                    ThemePreference::Light => light_path,
+                };
+                format!("point {} at {}", target.display(), source.display())
+            }
+        }
+    }
+}
+
+/// Replace occurrences of `from` with `to` in the provided file, if needed,
+/// writing atomically so a crash mid-write never truncates the file.
+fn replace_in_file(path: &Path, from: &str, to: &str, pattern: Option<&str>) -> Result<()> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+
+    let replaced = match pattern {
+        Some(pattern) => replace_matching(&contents, pattern, to)?,
+        None => replace_token(&contents, from, to),
+    };
+
+    if replaced == contents {
+        return Ok(());
+    }
+
+    debug!("Replacing `{}` with `{}` in {}", from, to, path.display());
+    let tmp = temp_sibling(path)?;
+    fs::write(&tmp, replaced).with_context(|| format!("writing {}", tmp.display()))?;
+    fs::rename(&tmp, path)
+        .with_context(|| format!("renaming {} to {}", tmp.display(), path.display()))
+}
+
+/// Replace whole-token occurrences of `from` with `to`. A match only counts
+/// as whole-token if it isn't immediately adjacent to another identifier
+/// character, so `from` being a prefix of `to` (e.g. `base16` vs
+/// `base16-dark`) can't cascade on repeated applies: once the file reads
+/// `base16-dark`, `base16` no longer occurs as a standalone token to match.
+fn replace_token(contents: &str, from: &str, to: &str) -> String {
+    if from.is_empty() {
+        return contents.to_string();
+    }
+
+    let mut out = String::with_capacity(contents.len());
+    let mut rest = contents;
+
+    while let Some(idx) = rest.find(from) {
+        let is_whole_token = is_whole_token_match(rest, idx, from.len());
+        out.push_str(&rest[..idx]);
+        out.push_str(if is_whole_token { to } else { from });
+        rest = &rest[idx + from.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Whether `token` occurs anywhere in `contents` as a whole token, using the
+/// same boundary rule as `replace_token`. Used by `check` so it doesn't flag
+/// e.g. `base16` as present just because `base16-dark` is.
+pub(crate) fn contains_token(contents: &str, token: &str) -> bool {
+    if token.is_empty() {
+        return false;
+    }
+
+    let mut rest = contents;
+    while let Some(idx) = rest.find(token) {
+        if is_whole_token_match(rest, idx, token.len()) {
+            return true;
+        }
+        rest = &rest[idx + token.len()..];
+    }
+    false
+}
+
+/// Whether the occurrence of a token at `rest[idx..idx + len]` is a whole
+/// token, i.e. not immediately adjacent to another identifier character.
+fn is_whole_token_match(rest: &str, idx: usize, len: usize) -> bool {
+    let is_token_char = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    let before = rest[..idx].chars().next_back();
+    let after = rest[idx + len..].chars().next();
+    !before.is_some_and(is_token_char) && !after.is_some_and(is_token_char)
+}
+
+/// Replace every match of a custom per-app regex with `to` verbatim (no
+/// `$1`-style capture expansion, since tokens are arbitrary theme names).
+fn replace_matching(contents: &str, pattern: &str, to: &str) -> Result<String> {
+    let re = Regex::new(pattern).with_context(|| format!("compiling regex `{pattern}`"))?;
+    Ok(re
+        .replace_all(contents, |_: &regex::Captures| to)
+        .into_owned())
+}
+
+/// A temporary sibling path for atomically swapping in a new version of `target`.
+fn temp_sibling(target: &Path) -> Result<PathBuf> {
+    let parent = target
+        .parent()
+        .with_context(|| format!("{} has no parent directory", target.display()))?;
+    let file_name = target
+        .file_name()
+        .with_context(|| format!("{} has no file name", target.display()))?
+        .to_string_lossy();
+    Ok(parent.join(format!(".{file_name}.theme-sync-tmp")))
+}
+
+/// Atomically re-point the `target` symlink at `source`, via a temporary
+/// symlink next to `target` plus a rename, so a crash never leaves `target`
+/// missing or half-written.
+fn symlink_swap(source: &Path, target: &Path) -> Result<()> {
+    let tmp = temp_sibling(target)?;
+
+    debug!("Pointing {} at {}", target.display(), source.display());
+
+    if fs::symlink_metadata(&tmp).is_ok() {
+        fs::remove_file(&tmp)
+            .with_context(|| format!("removing stale temporary symlink {}", tmp.display()))?;
+    }
+
+    symlink(source, &tmp)
+        .with_context(|| format!("creating temporary symlink {}", tmp.display()))?;
+
+    fs::rename(&tmp, target)
+        .with_context(|| format!("renaming {} to {}", tmp.display(), target.display()))?;
+
+    Ok(())
+}
+
+/// Set a single gsettings/dconf key to the given value.
+fn set_gsettings(schema: &str, key: &str, value: &str) -> Result<()> {
+    debug!("Setting {schema} {key} to {value}");
+    let status = Command::new("gsettings")
+        .args(["set", schema, key, value])
+        .status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        anyhow::bail!("gsettings set {schema} {key} {value} exited with status {status}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replace_token_does_not_cascade_when_from_is_a_prefix_of_to() {
+        let once = replace_token("theme = base16", "base16", "base16-dark");
+        assert_eq!(once, "theme = base16-dark");
+
+        let twice = replace_token(&once, "base16", "base16-dark");
+        assert_eq!(twice, once, "re-applying dark again must not double-append");
+    }
+
+    #[test]
+    fn replace_token_reapplying_same_theme_is_a_true_noop() {
+        let contents = "theme = base16-dark";
+        let replaced = replace_token(contents, "base16", "base16-dark");
+        assert_eq!(replaced, contents);
+    }
+
+    #[test]
+    fn replace_token_with_empty_from_is_a_noop() {
+        let contents = "theme = base16-dark";
+        assert_eq!(replace_token(contents, "", "anything"), contents);
+    }
+
+    #[test]
+    fn replace_token_leaves_unrelated_prefix_occurrences_alone() {
+        let contents = "theme = base16, other = base1600";
+        let replaced = replace_token(contents, "base16", "base16-dark");
+        assert_eq!(replaced, "theme = base16-dark, other = base1600");
+    }
+
+    #[test]
+    fn contains_token_agrees_with_replace_token_boundary_rule() {
+        let contents = "theme = base16-dark";
+        assert!(
+            !contains_token(contents, "base16"),
+            "base16 is only present as a prefix of base16-dark, not as a whole token"
+        );
+        assert!(contains_token(contents, "base16-dark"));
+    }
+
+    #[test]
+    fn contains_token_with_empty_token_is_false() {
+        assert!(!contains_token("anything", ""));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::backend::{Themeable, contains_token};
+use crate::config::{AppConfig, AppKind, Config};
+use crate::{ThemePreference, home_dir};
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+use std::fs;
+use std::path::Path;
+
+/// Validate every enabled app's on-disk state, optionally previewing what
+/// `apply` would change for each one without writing anything.
+pub fn check(config: &Config, dry_run: bool) -> Result<()> {
+    let mut problems = 0usize;
+
+    for app in &config.apps {
+        if app.disabled {
+            info!("{}: disabled, skipping", app.name);
+            continue;
+        }
+
+        problems += validate_app(app)?;
+
+        if dry_run {
+            for theme in [ThemePreference::Light, ThemePreference::Dark] {
+                info!(
+                    "{}: apply({}) would {}",
+                    app.name,
+                    theme.as_str(),
+                    app.kind.describe(theme)
+                );
+            }
+        }
+    }
+
+    if problems > 0 {
+        bail!("found {problems} problem(s); see warnings above");
+    }
+
+    info!("All configured apps look correct");
+    Ok(())
+}
+
+/// Check a single app's on-disk state, returning the number of problems found.
+fn validate_app(app: &AppConfig) -> Result<usize> {
+    match &app.kind {
+        AppKind::Replace {
+            path,
+            light_token,
+            dark_token,
+            ..
+        } => validate_replace(app, path, light_token, dark_token),
+        AppKind::Symlink {
+            light_path,
+            dark_path,
+            target,
+        } => validate_symlink(app, light_path, dark_path, target),
+        AppKind::Gsettings { .. } | AppKind::Command { .. } => Ok(0),
+    }
+}
+
+/// Check a `replace` app: the file must exist and hold exactly one of the two tokens.
+fn validate_replace(
+    app: &AppConfig,
+    path: &Path,
+    light_token: &str,
+    dark_token: &str,
+) -> Result<usize> {
+    let resolved = home_dir()?.join(path);
+    if !resolved.exists() {
+        warn!("{}: {} does not exist", app.name, resolved.display());
+        return Ok(1);
+    }
+
+    let contents =
+        fs::read_to_string(&resolved).with_context(|| format!("reading {}", resolved.display()))?;
+    let has_light = contains_token(&contents, light_token);
+    let has_dark = contains_token(&contents, dark_token);
+
+    match (has_light, has_dark) {
+        (true, false) | (false, true) => Ok(0),
+        (false, false) => {
+            warn!(
+                "{}: neither light_token nor dark_token found in {}",
+                app.name,
+                resolved.display()
+            );
+            Ok(1)
+        }
+        (true, true) => {
+            warn!(
+                "{}: both light_token and dark_token found in {} (ambiguous)",
+                app.name,
+                resolved.display()
+            );
+            Ok(1)
+        }
+    }
+}
+
+/// Check a `symlink` app: both variant files must exist, and `target` must be
+/// a symlink currently pointing at one of them.
+fn validate_symlink(
+    app: &AppConfig,
+    light_path: &Path,
+    dark_path: &Path,
+    target: &Path,
+) -> Result<usize> {
+    let home = home_dir()?;
+    let (light, dark, target) = (
+        home.join(light_path),
+        home.join(dark_path),
+        home.join(target),
+    );
+    let mut problems = 0;
+
+    for (field, path) in [("light_path", &light), ("dark_path", &dark)] {
+        if !path.exists() {
+            warn!("{}: {field} {} does not exist", app.name, path.display());
+            problems += 1;
+        }
+    }
+
+    match fs::read_link(&target) {
+        Ok(points_at) if points_at == light || points_at == dark => {}
+        Ok(points_at) => {
+            warn!(
+                "{}: target {} points at {} (neither light_path nor dark_path)",
+                app.name,
+                target.display(),
+                points_at.display()
+            );
+            problems += 1;
+        }
+        Err(_) => {
+            warn!(
+                "{}: target {} is missing or is not a symlink",
+                app.name,
+                target.display()
+            );
+            problems += 1;
+        }
+    }
+
+    Ok(problems)
+}
@@ -0,0 +1,61 @@
+use crate::Mode;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Configuration file schema.
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct Config {
+    /// Pin a variant instead of following the detected desktop preference.
+    #[serde(default)]
+    pub mode: Mode,
+    pub apps: Vec<AppConfig>,
+}
+
+/// Configuration for a single application. `kind` selects the themeing backend
+/// used to apply a variant, and carries the fields that backend needs.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AppConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: AppKind,
+    pub reload_cmd: Option<String>,
+    /// Skip this app entirely, without removing its configuration.
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// A themeing backend and the configuration it needs to apply a variant.
+///
+/// Analogous to how thcon abstracts each app behind a common trait, each
+/// variant here is one backend; `AppKind` implements `Themeable` once, by
+/// matching on the variant.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AppKind {
+    /// Swap a light/dark token in a plain text config file.
+    Replace {
+        path: PathBuf,
+        light_token: String,
+        dark_token: String,
+        /// Match tokens with this regex instead of the default word-boundary
+        /// heuristic, for file formats the heuristic gets wrong.
+        #[serde(default)]
+        regex: Option<String>,
+    },
+    /// Set a gsettings/dconf key to a named value per variant.
+    Gsettings {
+        schema: String,
+        key: String,
+        light_value: String,
+        dark_value: String,
+    },
+    /// Run a distinct shell command for each variant.
+    Command { light_cmd: String, dark_cmd: String },
+    /// Atomically re-point a symlink at one of two prebuilt variant files,
+    /// for apps themed by swapping a whole file rather than a token.
+    Symlink {
+        light_path: PathBuf,
+        dark_path: PathBuf,
+        target: PathBuf,
+    },
+}
@@ -1,27 +1,18 @@
 use anyhow::{Context, Error, Result, bail};
 use clap::{Parser, Subcommand, ValueEnum};
-use log::{debug, info, warn};
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::Command;
 
-/// Configuration file schema.
-#[derive(Debug, Deserialize, Serialize, Default)]
-struct Config {
-    apps: Vec<AppConfig>,
-}
+mod backend;
+mod check;
+mod config;
+mod preference;
 
-/// Configuration for a single application.
-#[derive(Debug, Deserialize, Serialize, Default)]
-struct AppConfig {
-    name: String,
-    path: PathBuf,
-    light_token: String,
-    dark_token: String,
-    reload_cmd: Option<String>,
-}
+use backend::Themeable;
+use config::{AppConfig, Config};
 
 /// Command line interface for synchronizing theme choices across tools.
 #[derive(Parser)]
@@ -30,6 +21,9 @@ struct Cli {
     /// Override the configuration file path.
     #[arg(short, long, value_name = "PATH")]
     config: Option<PathBuf>,
+    /// Override the configured mode for this invocation.
+    #[arg(long, value_enum)]
+    mode: Option<Mode>,
     #[command(subcommand)]
     command: CommandKind,
 }
@@ -37,23 +31,102 @@ struct Cli {
 /// Subcommands exposed by the CLI.
 #[derive(Subcommand)]
 enum CommandKind {
-    /// Watch GNOME theme preference changes and apply them live.
-    Monitor,
+    /// Watch desktop appearance preference changes and apply them live.
+    Monitor {
+        #[command(flatten)]
+        filter: AppFilterArgs,
+    },
     /// Apply a theme once, optionally overriding the detected preference.
     Set {
-        /// Explicit theme to apply instead of reading gsettings.
+        /// Explicit theme to apply instead of reading the desktop preference.
         #[arg(long, value_enum)]
         theme: Option<ThemePreference>,
+        #[command(flatten)]
+        filter: AppFilterArgs,
+    },
+    /// Validate the configuration: app paths exist and tokens are unambiguous.
+    Check {
+        /// Also preview what `apply` would change for each app, without writing.
+        #[arg(long)]
+        dry_run: bool,
     },
+    /// Write a commented starter configuration to the resolved config path.
+    GenerateConfig,
+}
+
+/// App selection shared by `Monitor` and `Set`.
+#[derive(clap::Args)]
+struct AppFilterArgs {
+    /// Only theme these apps (by config name); default is all enabled apps.
+    #[arg(long, value_name = "NAME")]
+    only: Vec<String>,
+    /// Skip theming these apps (by config name).
+    #[arg(long, value_name = "NAME")]
+    skip: Vec<String>,
+}
+
+impl AppFilterArgs {
+    /// Whether `name` should be themed under this `--only`/`--skip` selection.
+    fn matches(&self, name: &str) -> bool {
+        if !self.only.is_empty() && !self.only.iter().any(|n| n == name) {
+            return false;
+        }
+        !self.skip.iter().any(|n| n == name)
+    }
+
+    /// Warn about any `--only`/`--skip` name that doesn't match a configured
+    /// app, so a typo'd name doesn't silently theme zero apps.
+    fn warn_unknown_names(&self, config: &Config) {
+        for name in self.only.iter().chain(&self.skip) {
+            if !config.apps.iter().any(|app| &app.name == name) {
+                warn!("--only/--skip name `{name}` does not match any configured app");
+            }
+        }
+    }
 }
 
-/// Normalized theme preference tokens reported by GNOME.
+/// Normalized theme preference, regardless of which source reported it.
 #[derive(Copy, Clone, Debug, ValueEnum)]
 enum ThemePreference {
     Dark,
     Light,
 }
 
+impl ThemePreference {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ThemePreference::Dark => "dark",
+            ThemePreference::Light => "light",
+        }
+    }
+}
+
+/// Whether to follow the desktop's detected appearance preference, or pin a
+/// single variant regardless of it. Mirrors the light/dark/`mode` setting
+/// Zed added to its theme configuration.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    /// Follow the detected desktop preference.
+    #[default]
+    System,
+    /// Always apply the light variant.
+    Light,
+    /// Always apply the dark variant.
+    Dark,
+}
+
+impl Mode {
+    /// The variant this mode pins, or `None` in `system` mode.
+    fn pinned(self) -> Option<ThemePreference> {
+        match self {
+            Mode::System => None,
+            Mode::Light => Some(ThemePreference::Light),
+            Mode::Dark => Some(ThemePreference::Dark),
+        }
+    }
+}
+
 /// Applies theme updates for a single application defined in the configuration.
 struct Configurator<'a> {
     app: &'a AppConfig,
@@ -65,28 +138,11 @@ impl<'a> Configurator<'a> {
     }
 
     fn apply(&self, theme: ThemePreference) -> Result<()> {
-        let (from, to, variant) = match theme {
-            ThemePreference::Dark => (
-                self.app.light_token.as_str(),
-                self.app.dark_token.as_str(),
-                "dark",
-            ),
-            ThemePreference::Light => (
-                self.app.dark_token.as_str(),
-                self.app.light_token.as_str(),
-                "light",
-            ),
-        };
-
-        info!("Applying {} theme to {}", variant, self.app.name);
-
-        let home = std::env::var("SNAP_REAL_HOME")
-            .or_else(|_| std::env::var("HOME"))
-            .context("SNAP_REAL_HOME or HOME environment variable not set")?;
-
-        let path = Path::new(&home).join(&self.app.path);
-
-        replace_in_file(&path, from, to)
+        info!("Applying {} theme to {}", theme.as_str(), self.app.name);
+
+        self.app
+            .kind
+            .apply(theme)
             .with_context(|| format!("updating {} theme", self.app.name))?;
 
         if let Some(reload) = self.app.reload_cmd.as_deref()
@@ -103,106 +159,159 @@ fn main() -> Result<()> {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
     let cli = Cli::parse();
+
+    if matches!(cli.command, CommandKind::GenerateConfig) {
+        return generate_config(&resolved_config_path(cli.config.as_deref())?);
+    }
+
     let config = load_config(cli.config)?;
+    let mode = cli.mode.unwrap_or(config.mode);
 
     match cli.command {
-        CommandKind::Monitor => monitor_theme_changes(&config),
-        CommandKind::Set { theme } => set_once(theme, &config),
+        CommandKind::Monitor { filter } => {
+            filter.warn_unknown_names(&config);
+            monitor_theme_changes(mode, &config, &filter)
+        }
+        CommandKind::Set { theme, filter } => {
+            filter.warn_unknown_names(&config);
+            set_once(theme.or_else(|| mode.pinned()), &config, &filter)
+        }
+        CommandKind::Check { dry_run } => check::check(&config, dry_run),
+        CommandKind::GenerateConfig => unreachable!("handled above"),
     }
 }
 
-fn load_config(override_path: Option<PathBuf>) -> Result<Config, Error> {
-    if let Some(path) = override_path {
-        info!("Loading configuration from {}", path.display());
-        confy::load_path::<Config>(&path).context("loading configuration from override path")
-    } else {
-        // Confy's default path loading doesn't play well with snaps, so we emulate it here
-        // to account for both in-snap and out of snap invocations.
-        let home = std::env::var("SNAP_REAL_HOME")
-            .or_else(|_| std::env::var("HOME"))
-            .context("SNAP_REAL_HOME or HOME environment variable not set")?;
-        let path = Path::new(&home).join(".config/theme-sync/default-config.yml");
-
-        info!("Loading configuration from {}", path.display());
-        confy::load_path::<Config>(&path).context("loading configuration from default path")
+/// Resolve the user's home directory, accounting for snap confinement.
+fn home_dir() -> Result<PathBuf> {
+    std::env::var("SNAP_REAL_HOME")
+        .or_else(|_| std::env::var("HOME"))
+        .context("SNAP_REAL_HOME or HOME environment variable not set")
+        .map(PathBuf::from)
+}
+
+/// Resolve the configuration file path, defaulting under the user's home.
+fn resolved_config_path(override_path: Option<&Path>) -> Result<PathBuf> {
+    match override_path {
+        Some(path) => Ok(path.to_path_buf()),
+        None => {
+            // Confy's default path loading doesn't play well with snaps, so we emulate it
+            // here to account for both in-snap and out of snap invocations.
+            Ok(home_dir()?.join(".config/theme-sync/default-config.yml"))
+        }
     }
 }
 
-/// Stream GNOME theme preference updates and apply them to each tool.
-fn monitor_theme_changes(config: &Config) -> Result<()> {
-    let mut child = Command::new("gsettings")
-        .args(["monitor", "org.gnome.desktop.interface", "color-scheme"])
-        .stdout(Stdio::piped())
-        .spawn()?;
+fn load_config(override_path: Option<PathBuf>) -> Result<Config, Error> {
+    let path = resolved_config_path(override_path.as_deref())?;
+    info!("Loading configuration from {}", path.display());
+    confy::load_path::<Config>(&path).context("loading configuration")
+}
 
-    let stdout = child
-        .stdout
-        .take()
-        .context("failed to capture gsettings output")?;
+/// Write a commented starter configuration, refusing to clobber an existing file.
+fn generate_config(path: &Path) -> Result<()> {
+    if path.exists() {
+        bail!(
+            "{} already exists; remove it first if you want a fresh starter config",
+            path.display()
+        );
+    }
 
-    let reader = BufReader::new(stdout);
-    for line in reader.lines() {
-        let line = line?;
-        let theme = infer_theme(&line);
-        apply_all(theme, config)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
     }
 
-    child.wait()?;
+    fs::write(path, STARTER_CONFIG).with_context(|| format!("writing {}", path.display()))?;
+    info!("Wrote starter configuration to {}", path.display());
     Ok(())
 }
 
-/// Snapshot the current GNOME theme preference (or override) and apply it once.
-fn set_once(theme_override: Option<ThemePreference>, config: &Config) -> Result<()> {
-    let theme = match theme_override {
-        Some(theme) => theme,
-        None => {
-            let output = Command::new("gsettings")
-                .args(["get", "org.gnome.desktop.interface", "color-scheme"])
-                .output()?;
+const STARTER_CONFIG: &str = r#"# theme-sync configuration.
+#
+# `mode` controls how the desktop preference is resolved:
+#   system (default) - follow the detected light/dark preference
+#   light / dark      - always apply that variant, ignoring the desktop
+mode: system
 
-            if !output.status.success() {
-                bail!("gsettings get failed");
-            }
+# Each entry under `apps` themes one application. `kind` selects the backend:
+#   replace    - swap a token in a text config file (add `regex` to match
+#                tokens the default word-boundary heuristic gets wrong)
+#   gsettings  - set a gsettings/dconf key
+#   command    - run a distinct shell command per variant
+#   symlink    - atomically re-point a symlink at a prebuilt variant file
+apps: []
+#  - name: alacritty
+#    kind: replace
+#    path: .config/alacritty/alacritty.toml
+#    light_token: "catppuccin-latte"
+#    dark_token: "catppuccin-mocha"
+#    reload_cmd: "pkill -USR1 alacritty"
+#  - name: bat
+#    kind: replace
+#    path: .config/bat/config
+#    light_token: '--theme="Monokai Extended Light"'
+#    dark_token: '--theme="Monokai Extended"'
+#    regex: '--theme="[^"]*"'
+#  - name: gtk-theme
+#    kind: gsettings
+#    schema: org.gnome.desktop.interface
+#    key: gtk-theme
+#    light_value: Adwaita
+#    dark_value: Adwaita-dark
+#  - name: wallpaper
+#    kind: symlink
+#    light_path: .config/wallpaper/light.png
+#    dark_path: .config/wallpaper/dark.png
+#    target: .config/wallpaper/current.png
+"#;
 
-            let stdout = String::from_utf8(output.stdout)?;
-            infer_theme(&stdout)
+/// Watch desktop appearance preference updates and apply them to each tool.
+///
+/// In `system` mode this runs until killed, re-applying on every preference
+/// change. In `light`/`dark` mode the pinned variant is applied once and the
+/// detected preference is never consulted.
+fn monitor_theme_changes(mode: Mode, config: &Config, filter: &AppFilterArgs) -> Result<()> {
+    match mode.pinned() {
+        Some(theme) => apply_all(theme, config, filter),
+        None => {
+            let source = preference::detect_source();
+            source.watch(&mut |theme| apply_all(theme, config, filter))
         }
+    }
+}
+
+/// Snapshot the current appearance preference (or override) and apply it once.
+fn set_once(
+    theme_override: Option<ThemePreference>,
+    config: &Config,
+    filter: &AppFilterArgs,
+) -> Result<()> {
+    let theme = match theme_override {
+        Some(theme) => theme,
+        None => preference::detect_source().read()?,
     };
 
-    apply_all(theme, config)
+    apply_all(theme, config, filter)
 }
 
-/// Run every configurator for the supplied preference.
-fn apply_all(theme: ThemePreference, config: &Config) -> Result<()> {
+/// Run every enabled, selected configurator for the supplied preference.
+fn apply_all(theme: ThemePreference, config: &Config, filter: &AppFilterArgs) -> Result<()> {
     for app in &config.apps {
+        if app.disabled {
+            info!("Skipping disabled app {}", app.name);
+            continue;
+        }
+        if !filter.matches(&app.name) {
+            info!("Skipping {} (excluded by --only/--skip)", app.name);
+            continue;
+        }
         Configurator::new(app).apply(theme)?;
     }
     Ok(())
 }
 
-/// Infer a normalized theme choice from the gsettings output.
-fn infer_theme(input: &str) -> ThemePreference {
-    if input.contains("prefer-dark") {
-        ThemePreference::Dark
-    } else {
-        ThemePreference::Light
-    }
-}
-
-/// Replace occurrences of `from` with `to` in the provided file if needed.
-fn replace_in_file(path: &Path, from: &str, to: &str) -> Result<()> {
-    let contents = fs::read_to_string(path)?;
-    let replaced = contents.replace(from, to);
-    if replaced != contents {
-        debug!("Replacing `{}` with `{}` in {}", from, to, path.display());
-        fs::write(path, replaced)?;
-    }
-    Ok(())
-}
-
 /// Execute a shell command via `bash -c` and surface failures as anyhow errors.
 fn run_command(command: &str) -> Result<()> {
-    debug!("Running command: {}", command);
+    log::debug!("Running command: {}", command);
     let status = Command::new("bash").args(["-c", command]).status()?;
     if status.success() {
         Ok(())
@@ -0,0 +1,173 @@
+use crate::ThemePreference;
+use anyhow::{Context, Result, bail};
+use log::warn;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use zbus::blocking::Connection;
+use zbus::blocking::fdo::DBusProxy;
+use zbus::names::BusName;
+
+/// A source of desktop-wide light/dark appearance preference.
+pub trait PreferenceSource {
+    /// Read the current preference once.
+    fn read(&self) -> Result<ThemePreference>;
+
+    /// Block, invoking `on_change` each time the preference changes.
+    fn watch(&self, on_change: &mut dyn FnMut(ThemePreference) -> Result<()>) -> Result<()>;
+}
+
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+const COLOR_SCHEME_KEY: &str = "color-scheme";
+const PORTAL_SERVICE: &str = "org.freedesktop.portal.Desktop";
+
+#[zbus::proxy(
+    interface = "org.freedesktop.portal.Settings",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop",
+    gen_async = false
+)]
+trait Settings {
+    fn read_one(&self, namespace: &str, key: &str) -> zbus::Result<zbus::zvariant::OwnedValue>;
+
+    #[zbus(signal)]
+    fn setting_changed(
+        &self,
+        namespace: String,
+        key: String,
+        value: zbus::zvariant::OwnedValue,
+    ) -> zbus::Result<()>;
+}
+
+/// Reads and watches appearance preference via the XDG desktop portal
+/// (`org.freedesktop.portal.Settings`). Works across GNOME, KDE, Sway and
+/// any other portal-backed desktop, unlike shelling out to `gsettings`.
+pub struct PortalSource {
+    proxy: SettingsProxy<'static>,
+}
+
+impl PortalSource {
+    /// Connect to the session bus and the portal, failing fast if either is unreachable.
+    ///
+    /// `SettingsProxy::new` alone isn't enough to detect a portal-less desktop: it
+    /// succeeds even when nothing owns `org.freedesktop.portal.Desktop`, and only
+    /// fails later, at call time (or, for `watch`, hangs forever waiting on signals
+    /// that will never arrive). So probe the name ourselves before trusting it.
+    pub fn connect() -> Result<Self> {
+        let connection = Connection::session().context("connecting to the session bus")?;
+
+        if !portal_is_running(&connection)? {
+            bail!("{PORTAL_SERVICE} has no owner on the session bus");
+        }
+
+        let proxy = SettingsProxy::new(&connection)
+            .context("connecting to org.freedesktop.portal.Settings")?;
+        Ok(Self { proxy })
+    }
+}
+
+/// Whether anything currently owns the portal's well-known bus name.
+fn portal_is_running(connection: &Connection) -> Result<bool> {
+    let dbus = DBusProxy::new(connection).context("connecting to org.freedesktop.DBus")?;
+    let name = BusName::try_from(PORTAL_SERVICE).expect("PORTAL_SERVICE is a valid bus name");
+    dbus.name_has_owner(name)
+        .context("calling NameHasOwner(org.freedesktop.portal.Desktop)")
+}
+
+impl PreferenceSource for PortalSource {
+    fn read(&self) -> Result<ThemePreference> {
+        let value = self
+            .proxy
+            .read_one(APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY)
+            .context("calling ReadOne(org.freedesktop.appearance, color-scheme)")?;
+
+        let scheme: u32 = value.try_into().context("decoding color-scheme reply")?;
+        color_scheme_to_theme(scheme)
+    }
+
+    fn watch(&self, on_change: &mut dyn FnMut(ThemePreference) -> Result<()>) -> Result<()> {
+        for signal in self.proxy.receive_setting_changed()? {
+            let args = signal.args()?;
+            if args.namespace() != APPEARANCE_NAMESPACE || args.key() != COLOR_SCHEME_KEY {
+                continue;
+            }
+
+            let scheme: u32 = args
+                .value()
+                .try_into()
+                .context("decoding color-scheme reply")?;
+            on_change(color_scheme_to_theme(scheme)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Map a portal `color-scheme` value (0 = no preference, 1 = prefer-dark,
+/// 2 = prefer-light) onto our normalized preference.
+fn color_scheme_to_theme(scheme: u32) -> Result<ThemePreference> {
+    match scheme {
+        1 => Ok(ThemePreference::Dark),
+        0 | 2 => Ok(ThemePreference::Light),
+        other => bail!("unexpected color-scheme value {other}"),
+    }
+}
+
+/// Falls back to shelling out to `gsettings` on desktops with no portal
+/// `org.freedesktop.portal.Settings` implementation.
+pub struct GsettingsSource;
+
+impl PreferenceSource for GsettingsSource {
+    fn read(&self) -> Result<ThemePreference> {
+        let output = Command::new("gsettings")
+            .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+            .output()?;
+
+        if !output.status.success() {
+            bail!("gsettings get failed");
+        }
+
+        let stdout = String::from_utf8(output.stdout)?;
+        Ok(infer_theme(&stdout))
+    }
+
+    fn watch(&self, on_change: &mut dyn FnMut(ThemePreference) -> Result<()>) -> Result<()> {
+        let mut child = Command::new("gsettings")
+            .args(["monitor", "org.gnome.desktop.interface", "color-scheme"])
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdout = child
+            .stdout
+            .take()
+            .context("failed to capture gsettings output")?;
+
+        for line in BufReader::new(stdout).lines() {
+            on_change(infer_theme(&line?))?;
+        }
+
+        child.wait()?;
+        Ok(())
+    }
+}
+
+/// Infer a normalized theme choice from `gsettings get`/`monitor` output.
+fn infer_theme(input: &str) -> ThemePreference {
+    if input.contains("prefer-dark") {
+        ThemePreference::Dark
+    } else {
+        ThemePreference::Light
+    }
+}
+
+/// Pick the best available preference source: the portal when reachable,
+/// falling back to `gsettings` on GNOME-only or portal-less setups.
+pub fn detect_source() -> Box<dyn PreferenceSource> {
+    match PortalSource::connect() {
+        Ok(source) => Box::new(source),
+        Err(err) => {
+            warn!(
+                "org.freedesktop.portal.Settings unavailable, falling back to gsettings: {err:#}"
+            );
+            Box::new(GsettingsSource)
+        }
+    }
+}